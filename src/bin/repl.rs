@@ -0,0 +1,244 @@
+//! Interactive REPL for Simfony.
+//!
+//! Unlike the batch compiler, the REPL keeps a single [`GlobalScope`] alive
+//! across inputs together with the ordered history of `let`/function-call
+//! statements entered so far: each new entry is inferred and resolved
+//! against that whole history (inference and resolution are whole-program
+//! passes, so a later reference to an earlier `let` needs the full prefix,
+//! not just the one statement that introduces it), then a trailing
+//! expression is compiled and printed without being folded into the
+//! history itself.
+//!
+//! [`TypeAnnotations`] and [`simfony::resolve::Resolution`] are keyed by
+//! [`Span`](simfony::parse::Span), i.e. by byte range in whatever source text
+//! was parsed. Each line is handed to the parser on its own, so if every
+//! entry's span were relative to just that line, unrelated entries would
+//! very likely reuse the same low byte range and silently clobber each
+//! other's recorded type/path in the shared `HashMap`s. To keep every
+//! entry's spans unique across the whole session, each input is padded with
+//! enough leading spaces to continue where the running `offset` left off
+//! before it is parsed, so its spans land exactly where they would have if
+//! the whole history had been parsed as one file.
+
+use std::io::{self, BufRead, Write};
+
+use simfony::infer::{self, TypeAnnotations};
+use simfony::observer::NoopObserver;
+use simfony::parse::{self, Statement};
+use simfony::resolve;
+use simfony::scope::GlobalScope;
+
+fn main() {
+    println!("Simfony REPL. Enter `let` statements or a trailing expression.");
+    println!("Meta-commands: :type <expr>, :reset, :quit");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut scope = GlobalScope::new();
+    let mut history: Vec<Statement> = Vec::new();
+    let mut buffer = String::new();
+    // Byte offset the next entry's padding should continue from, so its
+    // spans don't collide with anything already in `history`. See the
+    // module doc comment for why this is needed at all.
+    let mut offset: usize = 0;
+
+    loop {
+        print_prompt(&buffer);
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("input error: {err}");
+                break;
+            }
+        };
+
+        if buffer.is_empty() {
+            if let Some(rest) = line.strip_prefix(":type ") {
+                match compile_type_of(rest, offset, &history, &mut scope.clone()) {
+                    Ok(ty) => println!("{ty}"),
+                    Err(err) => eprintln!("{err}"),
+                }
+                continue;
+            }
+            match line.trim() {
+                ":reset" => {
+                    scope = GlobalScope::new();
+                    history.clear();
+                    offset = 0;
+                    println!("scope cleared");
+                    continue;
+                }
+                ":quit" => break,
+                _ => {}
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+
+        if !is_balanced(&buffer) {
+            // Keep reading until brackets/braces close and the buffer forms
+            // a complete statement or expression.
+            continue;
+        }
+
+        match try_parse_entry(&buffer, offset) {
+            Ok(Entry::Statement(stmt)) => {
+                if let Err(err) = eval_statement(&stmt, &history, &mut scope) {
+                    eprintln!("{err}");
+                } else {
+                    // Only entries that join `history` need to keep their
+                    // spans reserved for good; a one-off expression below
+                    // never gets referenced again, so it doesn't advance
+                    // `offset`.
+                    offset += buffer.len();
+                    history.push(stmt);
+                }
+            }
+            Ok(Entry::Expression(expr)) => {
+                match eval_expression_blk(&history, &expr, &mut scope) {
+                    Ok((node, annotations)) => {
+                        let ty = annotations
+                            .get(expr.span)
+                            .map(|ty| ty.to_string())
+                            .unwrap_or_else(|| "<unresolved>".to_string());
+                        println!("{} : {ty}", node.arrow());
+                    }
+                    Err(err) => eprintln!("{err}"),
+                }
+            }
+            Err(ParseOutcome::Incomplete) => continue,
+            Err(ParseOutcome::Error(err)) => eprintln!("{err}"),
+        }
+        buffer.clear();
+    }
+}
+
+fn print_prompt(buffer: &str) {
+    print!("{}", if buffer.is_empty() { ">> " } else { ".. " });
+    let _ = io::stdout().flush();
+}
+
+/// A single REPL entry: either a `let`/function-call statement to fold into
+/// the persistent history, or a trailing expression to evaluate and discard.
+enum Entry {
+    Statement(Statement),
+    Expression(simfony::parse::Expression),
+}
+
+enum ParseOutcome {
+    /// The input is a well-formed prefix of a longer statement or
+    /// expression; keep buffering more lines.
+    Incomplete,
+    Error(String),
+}
+
+/// Pads `input` with `offset` leading spaces so its parsed spans continue
+/// from where the running history left off, instead of starting fresh at
+/// byte zero like every other standalone line would.
+fn pad_to_offset(input: &str, offset: usize) -> String {
+    let mut padded = " ".repeat(offset);
+    padded.push_str(input);
+    padded
+}
+
+fn try_parse_entry(input: &str, offset: usize) -> Result<Entry, ParseOutcome> {
+    let padded = pad_to_offset(input, offset);
+    if let Ok(stmt) = parse::parse_statement(&padded) {
+        return Ok(Entry::Statement(stmt));
+    }
+    match parse::parse_expression(&padded) {
+        Ok(expr) => Ok(Entry::Expression(expr)),
+        Err(err) => {
+            if err.is_incomplete() {
+                Err(ParseOutcome::Incomplete)
+            } else {
+                Err(ParseOutcome::Error(err.to_string()))
+            }
+        }
+    }
+}
+
+/// A line is "balanced" once every bracket it opens has been closed, so the
+/// REPL only attempts a parse once a statement or expression could plausibly
+/// be complete.
+fn is_balanced(input: &str) -> bool {
+    let mut depth: i32 = 0;
+    for c in input.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+/// Infers and resolves `stmt` against everything in `history`, then
+/// evaluates it against the persistent `scope`. The new statement is not
+/// appended to `history` here; the caller does that once evaluation
+/// succeeds, so a failed entry doesn't get remembered.
+fn eval_statement(
+    stmt: &Statement,
+    history: &[Statement],
+    scope: &mut GlobalScope,
+) -> Result<(), String> {
+    let mut stmts = history.to_vec();
+    stmts.push(stmt.clone());
+    let annotations =
+        infer::infer_program_with_tail(&stmts, None).map_err(|err| err.to_string())?;
+    let resolution =
+        resolve::resolve_program_with_tail(&stmts, None).map_err(|err| err.to_string())?;
+    match stmt {
+        Statement::Assignment(assignment) => {
+            let node = assignment
+                .expression
+                .eval(
+                    scope,
+                    &annotations,
+                    &resolution,
+                    &mut NoopObserver,
+                    assignment.ty.as_ref(),
+                )
+                .map_err(|err| err.to_string())?;
+            println!("{}", node.arrow());
+        }
+        Statement::FuncCall(func_call) => {
+            let node = func_call
+                .eval(scope, &annotations, &resolution, &mut NoopObserver, None)
+                .map_err(|err| err.to_string())?;
+            println!("{}", node.arrow());
+        }
+    }
+    Ok(())
+}
+
+fn eval_expression_blk(
+    history: &[Statement],
+    expr: &simfony::parse::Expression,
+    scope: &mut GlobalScope,
+) -> Result<(simfony::ProgNode, TypeAnnotations), String> {
+    let annotations = infer::infer_program_with_tail(history, Some(expr))
+        .map_err(|err| err.to_string())?;
+    let resolution = resolve::resolve_program_with_tail(history, Some(expr))
+        .map_err(|err| err.to_string())?;
+    let node = expr
+        .eval(scope, &annotations, &resolution, &mut NoopObserver, None)
+        .map_err(|err| err.to_string())?;
+    Ok((node, annotations))
+}
+
+fn compile_type_of(
+    input: &str,
+    offset: usize,
+    history: &[Statement],
+    scope: &mut GlobalScope,
+) -> Result<String, String> {
+    let padded = pad_to_offset(input, offset);
+    let expr = parse::parse_expression(&padded).map_err(|err| err.to_string())?;
+    let (node, _) = eval_expression_blk(history, &expr, scope)?;
+    Ok(node.arrow().to_string())
+}
@@ -0,0 +1,307 @@
+//! Built-in standard library functions dispatched by [`FuncType::BuiltIn`].
+//!
+//! Simplicity only allows bounded iteration, so `fold` and `map` cannot
+//! expand into an actual runtime loop. Instead they take the list argument's
+//! elements (known at compile time, since Simfony lists are always written
+//! as literals) and the per-element body, and expand into the same balanced
+//! `Partition`/`BTreeSlice` combinator tree that `SingleExpressionInner::List`
+//! already builds for list construction, applying the body at each leaf
+//! instead of just pairing elements up.
+
+use crate::array::{BTreeSlice, Partition};
+use crate::error::{Error, RichError, WithSpan};
+use crate::infer::TypeAnnotations;
+use crate::named::ProgExt;
+use crate::num::NonZeroPow2Usize;
+use crate::observer::Observer;
+use crate::parse::{Expression, ExpressionInner, Identifier, SingleExpressionInner, Span, Type};
+use crate::resolve::Resolution;
+use crate::scope::GlobalScope;
+use crate::ProgNode;
+
+/// The built-in functions known to the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltIn {
+    /// `fold(list, seed, body)`: reduce `list` to a single value, applying
+    /// `body: (element, accumulator) -> accumulator` right-to-left starting
+    /// from `seed`.
+    Fold,
+    /// `map(list, body)`: apply `body: element -> result` to every element,
+    /// producing a list of the same bound.
+    Map,
+}
+
+impl BuiltIn {
+    pub fn from_name(name: &Identifier) -> Option<Self> {
+        match name.as_inner().as_str() {
+            "fold" => Some(BuiltIn::Fold),
+            "map" => Some(BuiltIn::Map),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn arg_count(self) -> usize {
+        match self {
+            BuiltIn::Fold => 3,
+            BuiltIn::Map => 2,
+        }
+    }
+}
+
+/// Extracts the literal elements and bound of a list-typed argument.
+///
+/// Simfony lists are always written out as literals, so the elements (and
+/// hence the shape of the combinator tree `fold`/`map` build) are known at
+/// compile time; there is no runtime list value to iterate over.
+fn list_literal(expr: &Expression) -> Option<&[Expression]> {
+    match &expr.inner {
+        ExpressionInner::SingleExpression(single) => match &single.inner {
+            SingleExpressionInner::List(elements) => Some(elements),
+            _ => None,
+        },
+        ExpressionInner::BlockExpression(..) => None,
+    }
+}
+
+/// Dispatches a built-in call, validating the argument count and that the
+/// list argument is a literal before expanding the combinator tree.
+#[allow(clippy::too_many_arguments)]
+pub fn eval(
+    name: &Identifier,
+    args: &[Expression],
+    scope: &mut GlobalScope,
+    annotations: &TypeAnnotations,
+    resolution: &Resolution,
+    observer: &mut dyn Observer,
+    span: Span,
+) -> Result<ProgNode, RichError> {
+    let builtin = BuiltIn::from_name(name)
+        .ok_or_else(|| Error::FuncNotFound(name.clone()))
+        .with_span(span)?;
+    if args.len() != builtin.arg_count() {
+        return Err(Error::InvalidNumberOfArguments(
+            builtin.arg_count(),
+            args.len(),
+        ))
+        .with_span(span);
+    }
+    let elements = list_literal(&args[0])
+        .ok_or_else(|| Error::ExpectedListLiteral)
+        .with_span(span)?;
+    let bound = match annotations.get(args[0].span) {
+        Some(Type::List(_, bound)) => *bound,
+        _ => NonZeroPow2Usize::next(elements.len().saturating_add(1)),
+    };
+
+    match builtin {
+        BuiltIn::Map => eval_map(
+            elements, bound, &args[1], scope, annotations, resolution, observer, span,
+        ),
+        BuiltIn::Fold => eval_fold(
+            elements, bound, &args[1], &args[2], scope, annotations, resolution, observer, span,
+        ),
+    }
+}
+
+/// A closed body function is compiled in its own fresh witness scope and
+/// resolved against a brand new, empty variable scope (see
+/// `crate::resolve::resolve_builtin_call`): it is a standalone combinator,
+/// not a closure over the call site's variables, and a reference to one of
+/// them is rejected at resolution time rather than miscompiled here.
+///
+/// There is, however, no parameter binding for the element itself (or, for
+/// `fold`, the accumulator): nothing in this AST lets the body refer to
+/// either by name. In practice this means only a body that doesn't
+/// reference its element - a constant, or one built purely from witnesses -
+/// actually type-checks once `eval_map`/`eval_fold` compose it after the
+/// element.
+fn compile_body(
+    body: &Expression,
+    annotations: &TypeAnnotations,
+    resolution: &Resolution,
+    observer: &mut dyn Observer,
+) -> Result<ProgNode, RichError> {
+    body.eval(&mut GlobalScope::new(), annotations, resolution, observer, None)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn eval_map(
+    elements: &[Expression],
+    bound: NonZeroPow2Usize,
+    body: &Expression,
+    scope: &mut GlobalScope,
+    annotations: &TypeAnnotations,
+    resolution: &Resolution,
+    observer: &mut dyn Observer,
+    span: Span,
+) -> Result<ProgNode, RichError> {
+    let body_node = compile_body(body, annotations, resolution, observer)?;
+    let mapped: Vec<ProgNode> = elements
+        .iter()
+        .map(|el| {
+            let el_node = el.eval(scope, annotations, resolution, observer, None)?;
+            ProgNode::comp(&el_node, &body_node).with_span(span)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(map_tree(&mapped, bound))
+}
+
+/// Builds the balanced, `bound`-sized combinator tree for `map` out of the
+/// already-compiled, already-`body`-applied elements.
+///
+/// Pulled out of [`eval_map`] so the partition/padding shape can be unit
+/// tested directly on [`ProgNode`]s, without needing a parsed list literal.
+fn map_tree(mapped: &[ProgNode], bound: NonZeroPow2Usize) -> ProgNode {
+    let partition = Partition::from_slice(mapped, bound.get() / 2);
+    let process = |block: &[ProgNode]| -> ProgNode {
+        if block.is_empty() {
+            ProgNode::_false()
+        } else {
+            let tree = BTreeSlice::from_slice(block);
+            let array = tree.fold(|a, b| ProgNode::pair(&a, &b).unwrap());
+            ProgNode::injr(&array)
+        }
+    };
+    partition.fold(process, |a, b| ProgNode::pair(&a, &b).unwrap())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn eval_fold(
+    elements: &[Expression],
+    bound: NonZeroPow2Usize,
+    seed: &Expression,
+    body: &Expression,
+    scope: &mut GlobalScope,
+    annotations: &TypeAnnotations,
+    resolution: &Resolution,
+    observer: &mut dyn Observer,
+    span: Span,
+) -> Result<ProgNode, RichError> {
+    let body_node = compile_body(body, annotations, resolution, observer)?;
+    let seed_node = seed.eval(scope, annotations, resolution, observer, None)?;
+    let compiled: Vec<ProgNode> = elements
+        .iter()
+        .map(|el| el.eval(scope, annotations, resolution, observer, None))
+        .collect::<Result<_, _>>()?;
+
+    fold_tree(&compiled, bound, &seed_node, &body_node, span)
+}
+
+/// Builds the balanced, `bound`-sized combinator tree for `fold` out of the
+/// already-compiled elements, seed and body.
+///
+/// Pulled out of [`eval_fold`] so the partition/padding shape can be unit
+/// tested directly on [`ProgNode`]s, without needing a parsed list literal.
+fn fold_tree(
+    compiled: &[ProgNode],
+    bound: NonZeroPow2Usize,
+    seed_node: &ProgNode,
+    body_node: &ProgNode,
+    span: Span,
+) -> Result<ProgNode, RichError> {
+    // One fold step as an `(env, acc) -> acc` combinator: project the
+    // element's value out of `env`, pair it with the incoming accumulator,
+    // and hand both to the closed `body_node`.
+    let with_acc = |el_node: &ProgNode| -> Result<ProgNode, RichError> {
+        let elem = ProgNode::comp(&ProgNode::take(&ProgNode::iden()), el_node).with_span(span)?;
+        let pair = ProgNode::pair(&elem, &ProgNode::drop_(&ProgNode::iden())).with_span(span)?;
+        ProgNode::comp(&pair, body_node).with_span(span)
+    };
+    // Sequences two `(env, acc) -> acc` steps, running `first` then `second`
+    // while keeping `env` available to both.
+    let chain = |first: &ProgNode, second: &ProgNode| -> Result<ProgNode, RichError> {
+        let keep_env = ProgNode::pair(&ProgNode::take(&ProgNode::iden()), first).with_span(span)?;
+        ProgNode::comp(&keep_env, second).with_span(span)
+    };
+    // `drop_(iden())` is the `(env, acc) -> acc` identity: an empty block
+    // (padding past the literal's actual length, up to `bound`) passes the
+    // accumulator through unchanged instead of touching `body_node`.
+    let pass_through = ProgNode::drop_(&ProgNode::iden());
+
+    let partition = Partition::from_slice(compiled, bound.get() / 2);
+    // Within one block, fold right-to-left (highest index first, matching
+    // the documented semantics), by wrapping each new element as the new
+    // outermost step around the block's running result.
+    let process = |block: &[ProgNode]| -> ProgNode {
+        block
+            .iter()
+            .try_fold(pass_through.clone(), |acc, el_node| {
+                with_acc(el_node).and_then(|step| chain(&step, &acc))
+            })
+            .expect("take/drop/pair/comp over already-typed nodes cannot fail")
+    };
+    // Across blocks, the later (righter) block holds higher-index elements
+    // and must run first, so `right` is the outer step and `left` follows.
+    let block_fn = partition.fold(process, |left, right| {
+        chain(&right, &left).expect("take/drop/pair/comp over already-typed nodes cannot fail")
+    });
+
+    let env_and_seed = ProgNode::pair(&ProgNode::iden(), seed_node).with_span(span)?;
+    ProgNode::comp(&env_and_seed, &block_fn).with_span(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `ProgNode::unit()` ignores its input and always produces `()`, so its
+    // source type unifies with anything; using it for elements, seeds and
+    // bodies below lets these tests exercise the `Partition` shape itself
+    // without needing a parsed list literal.
+    fn leaf() -> ProgNode {
+        ProgNode::unit()
+    }
+
+    #[test]
+    fn map_tree_handles_exact_bound() {
+        let mapped = vec![leaf(), leaf(), leaf(), leaf()];
+        let bound = NonZeroPow2Usize::next(4);
+        map_tree(&mapped, bound);
+    }
+
+    #[test]
+    fn map_tree_pads_past_literal_length() {
+        // Bound exceeds the literal length: `Partition` must pad the
+        // remaining leaves rather than panic or silently drop them.
+        let mapped = vec![leaf(), leaf()];
+        let bound = NonZeroPow2Usize::next(4);
+        map_tree(&mapped, bound);
+    }
+
+    #[test]
+    fn map_tree_handles_empty_list() {
+        let bound = NonZeroPow2Usize::next(4);
+        map_tree(&[], bound);
+    }
+
+    #[test]
+    fn fold_tree_handles_exact_bound() {
+        let compiled = vec![leaf(), leaf(), leaf(), leaf()];
+        let bound = NonZeroPow2Usize::next(4);
+        let result = fold_tree(&compiled, bound, &leaf(), &leaf(), Span::default());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn fold_tree_pads_past_literal_length() {
+        // A bound bigger than the literal's length used to be silently
+        // ignored (the old `eval_fold` never looked at `bound` at all);
+        // this exercises the padded blocks that `process`'s `pass_through`
+        // identity now has to fold in alongside the real elements.
+        let compiled = vec![leaf(), leaf(), leaf()];
+        let bound = NonZeroPow2Usize::next(4);
+        let result = fold_tree(&compiled, bound, &leaf(), &leaf(), Span::default());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn fold_tree_handles_empty_list() {
+        // An empty list, still padded out to `bound`: every block is
+        // `pass_through`, so the result should just be the seed threaded
+        // through unchanged.
+        let bound = NonZeroPow2Usize::next(4);
+        let result = fold_tree(&[], bound, &leaf(), &leaf(), Span::default());
+        assert!(result.is_ok());
+    }
+}
@@ -6,8 +6,11 @@ use simplicity::node::{CoreConstructible as _, JetConstructible as _};
 use simplicity::{jet::Elements, Cmr, FailEntropy};
 
 use crate::array::{BTreeSlice, Partition};
+use crate::infer::{self, TypeAnnotations};
 use crate::num::NonZeroPow2Usize;
-use crate::parse::{Pattern, SingleExpressionInner, Span, UIntType};
+use crate::observer::{NoopObserver, Observer};
+use crate::parse::{SingleExpressionInner, Span, UIntType};
+use crate::resolve::{self, Resolution};
 use crate::{
     error::{Error, RichError, WithSpan},
     named::{ConstructExt, ProgExt},
@@ -16,29 +19,57 @@ use crate::{
     ProgNode,
 };
 
+#[allow(clippy::too_many_arguments)]
 fn eval_blk(
     stmts: &[Statement],
     scope: &mut GlobalScope,
+    annotations: &TypeAnnotations,
+    resolution: &Resolution,
+    observer: &mut dyn Observer,
     index: usize,
     last_expr: Option<&Expression>,
 ) -> Result<ProgNode, RichError> {
     if index >= stmts.len() {
         return match last_expr {
-            Some(expr) => expr.eval(scope, None),
+            Some(expr) => expr.eval(scope, annotations, resolution, observer, None),
             None => Ok(ProgNode::unit()),
         };
     }
     match &stmts[index] {
         Statement::Assignment(assignment) => {
-            let expr = assignment.expression.eval(scope, assignment.ty.as_ref())?;
-            scope.insert(assignment.pattern.clone());
+            let expr = assignment.expression.eval(
+                scope,
+                annotations,
+                resolution,
+                observer,
+                assignment.ty.as_ref(),
+            )?;
+            // No `scope.insert` here any more: `resolve::resolve_program` already
+            // assigned this binding its slot on the shared stack, so every
+            // `Variable` occurrence already knows exactly where to find it.
             let left = ProgNode::pair_iden(&expr);
-            let right = eval_blk(stmts, scope, index + 1, last_expr)?;
+            let right = eval_blk(
+                stmts,
+                scope,
+                annotations,
+                resolution,
+                observer,
+                index + 1,
+                last_expr,
+            )?;
             ProgNode::comp(&left, &right).with_span(assignment.span)
         }
         Statement::FuncCall(func_call) => {
-            let left = func_call.eval(scope, None)?;
-            let right = eval_blk(stmts, scope, index + 1, last_expr)?;
+            let left = func_call.eval(scope, annotations, resolution, observer, None)?;
+            let right = eval_blk(
+                stmts,
+                scope,
+                annotations,
+                resolution,
+                observer,
+                index + 1,
+                last_expr,
+            )?;
             combine_seq(&left, &right).with_span(func_call.span)
         }
     }
@@ -52,108 +83,188 @@ fn combine_seq(a: &ProgNode, b: &ProgNode) -> Result<ProgNode, simplicity::types
 
 impl Program {
     pub fn eval(&self, scope: &mut GlobalScope) -> Result<ProgNode, RichError> {
-        eval_blk(&self.statements, scope, 0, None)
+        self.eval_with_observer(scope, &mut NoopObserver)
+    }
+
+    /// Like [`Program::eval`] but calls into `observer` at each construction
+    /// site, so callers can trace or disassemble the generated Simplicity
+    /// program as it is built.
+    pub fn eval_with_observer(
+        &self,
+        scope: &mut GlobalScope,
+        observer: &mut dyn Observer,
+    ) -> Result<ProgNode, RichError> {
+        // Run type inference first so that integer-literal defaulting and
+        // match-arm unification are resolved up front, with spans, instead
+        // of being discovered as a panic or opaque error deep in `eval`.
+        let annotations = infer::infer_program(self)?;
+        // Resolve every variable occurrence to a precomputed environment
+        // path in one pass, so `eval` never searches a scope stack.
+        let resolution = resolve::resolve_program(self)?;
+        eval_blk(
+            &self.statements,
+            scope,
+            &annotations,
+            &resolution,
+            observer,
+            0,
+            None,
+        )
     }
 }
 
 impl FuncCall {
+    #[allow(clippy::too_many_arguments)]
     pub fn eval(
         &self,
         scope: &mut GlobalScope,
+        annotations: &TypeAnnotations,
+        resolution: &Resolution,
+        observer: &mut dyn Observer,
         _reqd_ty: Option<&Type>,
     ) -> Result<ProgNode, RichError> {
-        match &self.func_type {
+        let node = match &self.func_type {
             FuncType::Jet(name) => {
                 let args = match self.args.is_empty() {
                     true => SingleExpressionInner::Unit,
                     false => SingleExpressionInner::Array(self.args.clone()),
                 };
                 // TODO: Pass the jet source type here.
-                // FIXME: Constructing pairs should never fail because when Simfony is translated to
-                // Simplicity the input type is variable. However, the fact that pairs always unify
-                // is hard to prove at the moment, while Simfony lacks a type system.
-                let args_expr = args.eval(scope, None, self.span)?;
+                // `infer.rs` does not know individual jets' real signatures
+                // (those live in the external `simplicity` crate, not this
+                // AST), so it infers each argument independently rather than
+                // unifying them against the jet's actual source type. A
+                // mismatch here is still caught below, just reported at
+                // `self.span` rather than the specific wrong argument's span.
+                let args_expr =
+                    args.eval(scope, annotations, resolution, observer, None, self.span)?;
                 let jet = Elements::from_str(name.as_inner())
                     .map_err(|_| Error::JetDoesNotExist(name.as_inner().clone()))
                     .with_span(self.span)?;
                 let jet_expr = ProgNode::jet(jet);
-                ProgNode::comp(&args_expr, &jet_expr).with_span(self.span)
+                ProgNode::comp(&args_expr, &jet_expr).with_span(self.span)?
             }
-            FuncType::BuiltIn(..) => unimplemented!("Builtins are not supported yet"),
+            FuncType::BuiltIn(name) => crate::builtin::eval(
+                name,
+                &self.args,
+                scope,
+                annotations,
+                resolution,
+                observer,
+                self.span,
+            )?,
             FuncType::UnwrapLeft => {
                 debug_assert!(self.args.len() == 1);
-                let b = self.args[0].eval(scope, None)?;
+                let b = self.args[0].eval(scope, annotations, resolution, observer, None)?;
                 let left_and_unit = ProgNode::pair_unit(&b);
                 let fail_cmr = Cmr::fail(FailEntropy::ZERO);
                 let take_iden = ProgNode::take(&ProgNode::iden());
-                // FIXME: Assertions never fail to unify
-                // Fix upstream
+                // Safe to unwrap: `take_iden` is freshly typed, so `assertl` has
+                // nothing to conflict with. Whether `b` really is a sum is
+                // checked by the `comp` below.
                 let get_inner = ProgNode::assertl(&take_iden, fail_cmr).unwrap();
-                ProgNode::comp(&left_and_unit, &get_inner).with_span(self.span)
+                ProgNode::comp(&left_and_unit, &get_inner).with_span(self.span)?
             }
             FuncType::UnwrapRight | FuncType::Unwrap => {
                 debug_assert!(self.args.len() == 1);
-                let c = self.args[0].eval(scope, None)?;
+                let c = self.args[0].eval(scope, annotations, resolution, observer, None)?;
                 let right_and_unit = ProgNode::pair_unit(&c);
                 let fail_cmr = Cmr::fail(FailEntropy::ZERO);
                 let take_iden = ProgNode::take(&ProgNode::iden());
-                // FIXME: Assertions never fail to unify
-                // Fix upstream
+                // Safe to unwrap: `take_iden` is freshly typed, so `assertr` has
+                // nothing to conflict with. Whether `c` really is a sum is
+                // checked by the `comp` below.
                 let get_inner = ProgNode::assertr(fail_cmr, &take_iden).unwrap();
-                ProgNode::comp(&right_and_unit, &get_inner).with_span(self.span)
+                ProgNode::comp(&right_and_unit, &get_inner).with_span(self.span)?
             }
-        }
+        };
+        // No `observer.on_node_built` call here: when this is reached through
+        // a `SingleExpressionInner::FuncCall` arm, `SingleExpressionInner::eval`
+        // already reports the built node once, uniformly, for every variant.
+        // A statement-level call from `eval_blk` gets neither an
+        // `on_expression_start` nor an `on_node_built`, so the two stay paired.
+        Ok(node)
     }
 }
 
 impl Expression {
+    #[allow(clippy::too_many_arguments)]
     pub fn eval(
         &self,
         scope: &mut GlobalScope,
+        annotations: &TypeAnnotations,
+        resolution: &Resolution,
+        observer: &mut dyn Observer,
         reqd_ty: Option<&Type>,
     ) -> Result<ProgNode, RichError> {
         match &self.inner {
             ExpressionInner::BlockExpression(stmts, expr) => {
                 scope.push_scope();
-                let res = eval_blk(stmts, scope, 0, Some(expr.as_ref()));
+                let res = eval_blk(
+                    stmts,
+                    scope,
+                    annotations,
+                    resolution,
+                    observer,
+                    0,
+                    Some(expr.as_ref()),
+                );
                 scope.pop_scope();
                 res
             }
-            ExpressionInner::SingleExpression(e) => e.inner.eval(scope, reqd_ty, self.span),
+            ExpressionInner::SingleExpression(e) => e.inner.eval(
+                scope,
+                annotations,
+                resolution,
+                observer,
+                reqd_ty,
+                self.span,
+            ),
         }
     }
 }
 
 impl SingleExpressionInner {
+    #[allow(clippy::too_many_arguments)]
     pub fn eval(
         &self,
         scope: &mut GlobalScope,
+        annotations: &TypeAnnotations,
+        resolution: &Resolution,
+        observer: &mut dyn Observer,
         reqd_ty: Option<&Type>,
         span: Span,
     ) -> Result<ProgNode, RichError> {
+        observer.on_expression_start(self, span);
         let expr = match self {
             SingleExpressionInner::Unit => ProgNode::unit(),
             SingleExpressionInner::Left(l) => {
-                let l = l.eval(scope, None)?;
+                let l = l.eval(scope, annotations, resolution, observer, None)?;
                 ProgNode::injl(&l)
             }
             SingleExpressionInner::None => ProgNode::_false(),
             SingleExpressionInner::Right(r) | SingleExpressionInner::Some(r) => {
-                let r = r.eval(scope, None)?;
+                let r = r.eval(scope, annotations, resolution, observer, None)?;
                 ProgNode::injr(&r)
             }
             SingleExpressionInner::False => ProgNode::_false(),
             SingleExpressionInner::True => ProgNode::_true(),
             SingleExpressionInner::Product(l, r) => {
-                let l = l.eval(scope, None)?;
-                let r = r.eval(scope, None)?;
-                // FIXME: Constructing pairs should never fail because when Simfony is translated to
-                // Simplicity the input type is variable. However, the fact that pairs always unify
-                // is hard to prove at the moment, while Simfony lacks a type system.
+                let l = l.eval(scope, annotations, resolution, observer, None)?;
+                let r = r.eval(scope, annotations, resolution, observer, None)?;
+                // Inference already checked that `l` and `r` unify with the
+                // product type this node was annotated with; any remaining
+                // failure is still reported with this node's span.
                 ProgNode::pair(&l, &r).with_span(span)?
             }
             SingleExpressionInner::UnsignedInteger(decimal) => {
-                let reqd_ty = reqd_ty.cloned().unwrap_or(Type::UInt(UIntType::U32));
+                // `reqd_ty` wins when the surrounding context pins a width; otherwise
+                // fall back to what inference resolved the literal to (which itself
+                // defaults to `U32` only if the literal was never constrained).
+                let reqd_ty = reqd_ty
+                    .cloned()
+                    .or_else(|| annotations.get(span).cloned())
+                    .unwrap_or(Type::UInt(UIntType::U32));
                 let ty = reqd_ty
                     .to_uint()
                     .ok_or(Error::TypeValueMismatch(reqd_ty))
@@ -173,40 +284,44 @@ impl SingleExpressionInner {
                 scope.insert_witness(name.clone());
                 ProgNode::witness(name.as_inner().clone())
             }
-            SingleExpressionInner::Variable(identifier) => scope
-                .get(identifier)
-                .ok_or(Error::UndefinedVariable(identifier.clone()))
-                .with_span(span)?,
-            SingleExpressionInner::FuncCall(call) => call.eval(scope, reqd_ty)?,
-            SingleExpressionInner::Expression(expression) => expression.eval(scope, reqd_ty)?,
+            SingleExpressionInner::Variable(identifier) => {
+                // The whole program was resolved once up front, so this is
+                // just building the stored path's `take`/`drop` chain — no
+                // scope stack to search here.
+                let path = resolution
+                    .get(span)
+                    .ok_or(Error::UndefinedVariable(identifier.clone()))
+                    .with_span(span)?;
+                let node = path.to_program();
+                observer.on_scope_lookup(identifier, &node);
+                node
+            }
+            SingleExpressionInner::FuncCall(call) => {
+                call.eval(scope, annotations, resolution, observer, reqd_ty)?
+            }
+            SingleExpressionInner::Expression(expression) => {
+                expression.eval(scope, annotations, resolution, observer, reqd_ty)?
+            }
             SingleExpressionInner::Match {
                 scrutinee,
                 left,
                 right,
             } => {
-                let mut l_scope = scope.clone();
-                l_scope.insert(
-                    left.pattern
-                        .get_identifier()
-                        .cloned()
-                        .map(Pattern::Identifier)
-                        .unwrap_or(Pattern::Ignore),
-                );
-                let l_compiled = left.expression.eval(&mut l_scope, reqd_ty)?;
-
-                let mut r_scope = scope.clone();
-                r_scope.insert(
+                // No `scope.clone()` per arm: the resolver already gave the
+                // bound pattern its own slot on the shared binding stack, so
+                // both arms can compile against the very same `scope`.
+                let l_compiled =
+                    left.expression
+                        .eval(scope, annotations, resolution, observer, reqd_ty)?;
+                let r_compiled =
                     right
-                        .pattern
-                        .get_identifier()
-                        .cloned()
-                        .map(Pattern::Identifier)
-                        .unwrap_or(Pattern::Ignore),
-                );
-                let r_compiled = right.expression.eval(&mut r_scope, reqd_ty)?;
+                        .expression
+                        .eval(scope, annotations, resolution, observer, reqd_ty)?;
 
-                // TODO: Enforce target type A + B for m_expr
-                let scrutinized_input = scrutinee.eval(scope, None)?;
+                // The scrutinee's sum type and both arms' result types were already
+                // unified during inference; this is just emitting the combinators.
+                let scrutinized_input =
+                    scrutinee.eval(scope, annotations, resolution, observer, None)?;
                 let input = ProgNode::pair_iden(&scrutinized_input);
                 let output = ProgNode::case(&l_compiled, &r_compiled).with_span(span)?;
                 ProgNode::comp(&input, &output).with_span(span)?
@@ -219,7 +334,10 @@ impl SingleExpressionInner {
                 };
                 let nodes: Vec<_> = elements
                     .iter()
-                    .map(|e| e.eval(scope, el_type).unwrap())
+                    .map(|e| {
+                        e.eval(scope, annotations, resolution, observer, el_type)
+                            .unwrap()
+                    })
                     .collect();
                 let tree = BTreeSlice::from_slice(&nodes);
                 tree.fold(|a, b| ProgNode::pair(&a, &b).unwrap())
@@ -232,7 +350,10 @@ impl SingleExpressionInner {
                 };
                 let nodes: Vec<_> = elements
                     .iter()
-                    .map(|e| e.eval(scope, el_type).unwrap())
+                    .map(|e| {
+                        e.eval(scope, annotations, resolution, observer, el_type)
+                            .unwrap()
+                    })
                     .collect();
                 let bound = if let Some(Type::List(_, bound)) = reqd_ty {
                     *bound
@@ -261,6 +382,7 @@ impl SingleExpressionInner {
                 .map_err(|_| Error::TypeValueMismatch(reqd_ty.clone()))
                 .with_span(span)?;
         }
+        observer.on_node_built(&expr, span);
         Ok(expr)
     }
 }
@@ -0,0 +1,675 @@
+//! Hindley-Milner style type inference over the Simfony AST.
+//!
+//! This pass runs before [`crate::compile`] and resolves every node to a concrete
+//! [`Type`], so that constructs which the compiler previously assumed to unify
+//! (pairs, match arms, integer literal defaults) are checked up front with a
+//! span instead of panicking or producing an opaque [`Error::TypeValueMismatch`]
+//! deep inside Simplicity's own unification.
+
+use std::collections::HashMap;
+
+use crate::parse::{
+    Expression, ExpressionInner, FuncCall, FuncType, Identifier, Pattern, Program,
+    SingleExpressionInner, Span, Statement, Type, UIntType,
+};
+use crate::{
+    error::{Error, RichError, WithSpan},
+    num::NonZeroPow2Usize,
+};
+
+/// Identifier of a fresh unification variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeVar(usize);
+
+/// A type term during inference: either a concrete constructor or an
+/// unresolved unification variable.
+///
+/// This mirrors [`Type`] exactly, plus the [`Term::Var`] case. Integer
+/// literals are given [`Term::Var`]s that additionally carry a "must be some
+/// `UInt`" constraint, tracked separately in [`Context::uint_vars`] so that a
+/// variable can be resolved to any width without committing to one early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Unit,
+    Sum(Box<Term>, Box<Term>),
+    Product(Box<Term>, Box<Term>),
+    UInt(UIntType),
+    Array(Box<Term>, usize),
+    List(Box<Term>, NonZeroPow2Usize),
+    Var(TypeVar),
+}
+
+impl Term {
+    fn resolve(&self, subst: &Substitution) -> Term {
+        match self {
+            Term::Var(v) => match subst.get(*v) {
+                Some(t) => t.resolve(subst),
+                None => self.clone(),
+            },
+            Term::Sum(l, r) => Term::Sum(
+                Box::new(l.resolve(subst)),
+                Box::new(r.resolve(subst)),
+            ),
+            Term::Product(l, r) => Term::Product(
+                Box::new(l.resolve(subst)),
+                Box::new(r.resolve(subst)),
+            ),
+            Term::Array(el, n) => Term::Array(Box::new(el.resolve(subst)), *n),
+            Term::List(el, bound) => Term::List(Box::new(el.resolve(subst)), *bound),
+            Term::Unit | Term::UInt(..) => self.clone(),
+        }
+    }
+
+    /// Converts a fully resolved term into a concrete [`Type`].
+    ///
+    /// Any variable still present at this point is defaulted to `U32`,
+    /// mirroring the previous hard-coded behaviour for unconstrained integer
+    /// literals.
+    fn into_type(self) -> Type {
+        match self {
+            Term::Unit => Type::Unit,
+            Term::Sum(l, r) => Type::Sum(Box::new(l.into_type()), Box::new(r.into_type())),
+            Term::Product(l, r) => {
+                Type::Product(Box::new(l.into_type()), Box::new(r.into_type()))
+            }
+            Term::UInt(ty) => Type::UInt(ty),
+            Term::Array(el, n) => Type::Array(Box::new(el.into_type()), n),
+            Term::List(el, bound) => Type::List(Box::new(el.into_type()), bound),
+            Term::Var(..) => Type::UInt(UIntType::U32),
+        }
+    }
+
+    fn from_type(ty: &Type) -> Term {
+        match ty {
+            Type::Unit => Term::Unit,
+            Type::Sum(l, r) => Term::Sum(Box::new(Term::from_type(l)), Box::new(Term::from_type(r))),
+            Type::Product(l, r) => {
+                Term::Product(Box::new(Term::from_type(l)), Box::new(Term::from_type(r)))
+            }
+            Type::UInt(ty) => Term::UInt(*ty),
+            Type::Array(el, n) => Term::Array(Box::new(Term::from_type(el)), *n),
+            Type::List(el, bound) => Term::List(Box::new(Term::from_type(el)), *bound),
+        }
+    }
+}
+
+/// A substitution from type variables to resolved terms, built up as
+/// [`unify`] runs.
+#[derive(Debug, Default)]
+struct Substitution {
+    map: HashMap<TypeVar, Term>,
+}
+
+impl Substitution {
+    fn get(&self, var: TypeVar) -> Option<&Term> {
+        self.map.get(&var)
+    }
+
+    fn bind(&mut self, var: TypeVar, term: Term) {
+        debug_assert!(!self.map.contains_key(&var));
+        self.map.insert(var, term);
+    }
+}
+
+/// Resolved type annotations, keyed by the [`Span`] of the AST node they were
+/// computed for. [`crate::compile`] consults this map wherever it previously
+/// had to guess (most notably, defaulting an unconstrained integer literal).
+#[derive(Debug, Default, Clone)]
+pub struct TypeAnnotations {
+    spans: HashMap<Span, Type>,
+}
+
+impl TypeAnnotations {
+    pub fn get(&self, span: Span) -> Option<&Type> {
+        self.spans.get(&span)
+    }
+}
+
+/// Inference context: a fresh-variable counter, the running substitution and
+/// a scope of identifier bindings mirroring [`crate::scope::GlobalScope`]'s
+/// stack-of-scopes shape.
+struct Context {
+    subst: Substitution,
+    next_var: usize,
+    bindings: Vec<Vec<(Identifier, Term)>>,
+    annotations: HashMap<Span, Term>,
+    /// Variables created for an unconstrained integer literal: these must
+    /// resolve to *some* [`Term::UInt`], even if nothing else pins down
+    /// which width, so a literal assigned to a non-integer type (e.g. an
+    /// array) is rejected here instead of only in `compile.rs`'s `to_uint()`
+    /// check, which baseline already performed.
+    uint_vars: std::collections::HashSet<TypeVar>,
+}
+
+impl Context {
+    fn new() -> Self {
+        Context {
+            subst: Substitution::default(),
+            next_var: 0,
+            bindings: vec![Vec::new()],
+            annotations: HashMap::new(),
+            uint_vars: std::collections::HashSet::new(),
+        }
+    }
+
+    fn fresh(&mut self) -> Term {
+        let var = TypeVar(self.next_var);
+        self.next_var += 1;
+        Term::Var(var)
+    }
+
+    /// Like [`Self::fresh`], but the variable is additionally constrained to
+    /// resolve to some `UInt` width.
+    fn fresh_uint(&mut self) -> Term {
+        let var = match self.fresh() {
+            Term::Var(var) => var,
+            _ => unreachable!("fresh always returns Term::Var"),
+        };
+        self.uint_vars.insert(var);
+        Term::Var(var)
+    }
+
+    fn push_scope(&mut self) {
+        self.bindings.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.bindings.pop().expect("popping scope zero");
+    }
+
+    fn insert(&mut self, pattern: &Pattern, term: Term) {
+        if let Pattern::Identifier(identifier) = pattern {
+            self.bindings
+                .last_mut()
+                .unwrap()
+                .push((identifier.clone(), term));
+        }
+    }
+
+    fn lookup(&self, identifier: &Identifier) -> Option<Term> {
+        self.bindings
+            .iter()
+            .rev()
+            .find_map(|scope| {
+                scope
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| name == identifier)
+                    .map(|(_, term)| term.clone())
+            })
+    }
+
+    fn record(&mut self, span: Span, term: Term) {
+        self.annotations.insert(span, term);
+    }
+
+    /// Unifies `lhs` and `rhs`, resolving both through the current
+    /// substitution first.
+    ///
+    /// Binding a variable runs the occurs check to reject infinite types;
+    /// two matching constructors recurse structurally; anything else is a
+    /// type mismatch.
+    fn unify(&mut self, lhs: &Term, rhs: &Term, span: Span) -> Result<(), RichError> {
+        let lhs = lhs.resolve(&self.subst);
+        let rhs = rhs.resolve(&self.subst);
+        match (&lhs, &rhs) {
+            (Term::Var(a), Term::Var(b)) if a == b => Ok(()),
+            (Term::Var(a), _) => self.bind(*a, rhs, span),
+            (_, Term::Var(b)) => self.bind(*b, lhs, span),
+            (Term::Unit, Term::Unit) => Ok(()),
+            (Term::UInt(a), Term::UInt(b)) if a == b => Ok(()),
+            (Term::Sum(a1, a2), Term::Sum(b1, b2))
+            | (Term::Product(a1, a2), Term::Product(b1, b2)) => {
+                self.unify(a1, b1, span)?;
+                self.unify(a2, b2, span)
+            }
+            (Term::Array(a, n), Term::Array(b, m)) if n == m => self.unify(a, b, span),
+            // Unlike an array's length, a list's `bound` is never a real
+            // constraint to unify: it's a compile-time padding capacity
+            // (`compile.rs`'s `SingleExpressionInner::List` arm takes its
+            // padding target straight from the caller-supplied `reqd_ty`,
+            // never from this term or `TypeAnnotations`), and a literal's
+            // own bound is always computed bottom-up from its length with
+            // no variable to later widen to a larger declared bound. So two
+            // list terms are compatible whenever their element types are,
+            // regardless of bound; only the element type is worth unifying.
+            (Term::List(a, _), Term::List(b, _)) => self.unify(a, b, span),
+            _ => Err(Error::TypeValueMismatch(lhs.into_type())).with_span(span),
+        }
+    }
+
+    fn bind(&mut self, var: TypeVar, term: Term, span: Span) -> Result<(), RichError> {
+        if occurs(var, &term, &self.subst) {
+            return Err(Error::TypeValueMismatch(term.into_type())).with_span(span);
+        }
+        if self.uint_vars.contains(&var) {
+            match &term {
+                Term::UInt(..) => {}
+                // Binding to another unresolved variable: propagate the
+                // constraint forward instead of losing it.
+                Term::Var(other) => {
+                    self.uint_vars.insert(*other);
+                }
+                _ => return Err(Error::TypeValueMismatch(term.into_type())).with_span(span),
+            }
+        }
+        self.subst.bind(var, term);
+        Ok(())
+    }
+}
+
+fn occurs(var: TypeVar, term: &Term, subst: &Substitution) -> bool {
+    match term.resolve(subst) {
+        Term::Var(v) => v == var,
+        Term::Sum(l, r) | Term::Product(l, r) => occurs(var, &l, subst) || occurs(var, &r, subst),
+        Term::Array(el, ..) | Term::List(el, ..) => occurs(var, &el, subst),
+        Term::Unit | Term::UInt(..) => false,
+    }
+}
+
+/// Runs Algorithm W over `program`, returning the resolved type of every
+/// node or the first unification error encountered.
+pub fn infer_program(program: &Program) -> Result<TypeAnnotations, RichError> {
+    infer_program_with_tail(&program.statements, None)
+}
+
+/// Like [`infer_program`], but additionally infers a trailing expression
+/// that is not itself one of `stmts` (e.g. the REPL's next input, evaluated
+/// against everything entered so far without being stored as a statement).
+pub fn infer_program_with_tail(
+    stmts: &[Statement],
+    tail: Option<&Expression>,
+) -> Result<TypeAnnotations, RichError> {
+    let mut ctx = Context::new();
+    infer_block(&mut ctx, stmts, tail)?;
+    let annotations = ctx
+        .annotations
+        .iter()
+        .map(|(span, term)| (*span, term.resolve(&ctx.subst).into_type()))
+        .collect();
+    Ok(TypeAnnotations { spans: annotations })
+}
+
+fn infer_block(
+    ctx: &mut Context,
+    stmts: &[Statement],
+    tail: Option<&Expression>,
+) -> Result<Term, RichError> {
+    for stmt in stmts {
+        match stmt {
+            Statement::Assignment(assignment) => {
+                let reqd = assignment.ty.as_ref().map(Term::from_type);
+                let actual = infer_expression(ctx, &assignment.expression)?;
+                if let Some(reqd) = reqd {
+                    ctx.unify(&reqd, &actual, assignment.span)?;
+                }
+                ctx.insert(&assignment.pattern, actual);
+            }
+            Statement::FuncCall(func_call) => {
+                infer_func_call(ctx, func_call, None)?;
+            }
+        }
+    }
+    match tail {
+        Some(expr) => infer_expression(ctx, expr),
+        None => Ok(Term::Unit),
+    }
+}
+
+fn infer_expression(ctx: &mut Context, expr: &Expression) -> Result<Term, RichError> {
+    let term = match &expr.inner {
+        ExpressionInner::BlockExpression(stmts, tail) => {
+            ctx.push_scope();
+            let result = infer_block(ctx, stmts, Some(tail.as_ref()));
+            ctx.pop_scope();
+            result?
+        }
+        ExpressionInner::SingleExpression(single) => {
+            infer_single(ctx, &single.inner, expr.span)?
+        }
+    };
+    ctx.record(expr.span, term.clone());
+    Ok(term)
+}
+
+fn infer_single(
+    ctx: &mut Context,
+    inner: &SingleExpressionInner,
+    span: Span,
+) -> Result<Term, RichError> {
+    let term = match inner {
+        SingleExpressionInner::Unit => Term::Unit,
+        SingleExpressionInner::Left(l) => {
+            let l_ty = infer_expression(ctx, l)?;
+            Term::Sum(Box::new(l_ty), Box::new(ctx.fresh()))
+        }
+        SingleExpressionInner::None => Term::Sum(Box::new(Term::Unit), Box::new(ctx.fresh())),
+        SingleExpressionInner::Right(r) | SingleExpressionInner::Some(r) => {
+            let r_ty = infer_expression(ctx, r)?;
+            Term::Sum(Box::new(ctx.fresh()), Box::new(r_ty))
+        }
+        // `false`/`true` compile to `ProgNode::_false()`/`_true()`
+        // (`compile.rs`'s `SingleExpressionInner::False`/`True` arms), the
+        // same `Sum(Unit, Unit)` encoding as `None` just above - not `Unit`
+        // itself. Inferring `Unit` here rejected any program that unifies a
+        // bool against a sum-shaped expectation (a `match` over a bool, or
+        // assigning a bool where something else is later unified as a sum)
+        // even though it compiled fine before this pass existed.
+        SingleExpressionInner::False | SingleExpressionInner::True => {
+            Term::Sum(Box::new(Term::Unit), Box::new(Term::Unit))
+        }
+        SingleExpressionInner::Product(l, r) => {
+            let l_ty = infer_expression(ctx, l)?;
+            let r_ty = infer_expression(ctx, r)?;
+            Term::Product(Box::new(l_ty), Box::new(r_ty))
+        }
+        SingleExpressionInner::UnsignedInteger(..) => {
+            // A fresh variable constrained to "some UInt" (see
+            // `Context::uint_vars`), resolved later; no hard-coded `U32`
+            // default until the whole pass has run, but binding it to a
+            // non-integer type is caught right here instead of only
+            // surfacing later as a `to_uint()` failure in `compile.rs`.
+            ctx.fresh_uint()
+        }
+        SingleExpressionInner::BitString(bits) => Term::from_type(&bits.ty()),
+        SingleExpressionInner::ByteString(bytes) => Term::from_type(&bytes.ty()),
+        SingleExpressionInner::Witness(..) => ctx.fresh(),
+        SingleExpressionInner::Variable(identifier) => ctx
+            .lookup(identifier)
+            .ok_or(Error::UndefinedVariable(identifier.clone()))
+            .with_span(span)?,
+        SingleExpressionInner::FuncCall(call) => infer_func_call(ctx, call, None)?,
+        SingleExpressionInner::Expression(expression) => infer_expression(ctx, expression)?,
+        SingleExpressionInner::Match {
+            scrutinee,
+            left,
+            right,
+        } => {
+            let scrutinee_ty = infer_expression(ctx, scrutinee)?;
+            let left_var = ctx.fresh();
+            let right_var = ctx.fresh();
+            ctx.unify(
+                &scrutinee_ty,
+                &Term::Sum(Box::new(left_var.clone()), Box::new(right_var.clone())),
+                span,
+            )?;
+
+            ctx.push_scope();
+            ctx.insert(&left.pattern, left_var);
+            let left_ty = infer_expression(ctx, &left.expression)?;
+            ctx.pop_scope();
+
+            ctx.push_scope();
+            ctx.insert(&right.pattern, right_var);
+            let right_ty = infer_expression(ctx, &right.expression)?;
+            ctx.pop_scope();
+
+            ctx.unify(&left_ty, &right_ty, span)?;
+            left_ty
+        }
+        SingleExpressionInner::Array(elements) => {
+            let el_var = ctx.fresh();
+            for el in elements {
+                let el_ty = infer_expression(ctx, el)?;
+                ctx.unify(&el_var, &el_ty, span)?;
+            }
+            Term::Array(Box::new(el_var), elements.len())
+        }
+        SingleExpressionInner::List(elements) => {
+            let el_var = ctx.fresh();
+            for el in elements {
+                let el_ty = infer_expression(ctx, el)?;
+                ctx.unify(&el_var, &el_ty, span)?;
+            }
+            let bound = NonZeroPow2Usize::next(elements.len().saturating_add(1));
+            Term::List(Box::new(el_var), bound)
+        }
+    };
+    Ok(term)
+}
+
+fn infer_func_call(
+    ctx: &mut Context,
+    call: &FuncCall,
+    _reqd: Option<&Type>,
+) -> Result<Term, RichError> {
+    match &call.func_type {
+        FuncType::Jet(..) => {
+            // No signature table exists here for individual jets: their
+            // source/target types are owned by the external `simplicity`
+            // crate, not this AST, so each argument is inferred
+            // independently and the call's own type is an unconstrained
+            // variable. A real mismatch is still caught, just without a
+            // span pinned to the offending argument: `ProgNode::comp` in
+            // `compile.rs` unifies the packed arguments against the jet's
+            // actual source type once the jet itself has been resolved.
+            for arg in &call.args {
+                infer_expression(ctx, arg)?;
+            }
+            Ok(ctx.fresh())
+        }
+        FuncType::BuiltIn(name) => infer_builtin(ctx, name, &call.args, call.span),
+        FuncType::UnwrapLeft => {
+            debug_assert!(call.args.len() == 1);
+            let arg_ty = infer_expression(ctx, &call.args[0])?;
+            let left_var = ctx.fresh();
+            ctx.unify(
+                &arg_ty,
+                &Term::Sum(Box::new(left_var.clone()), Box::new(ctx.fresh())),
+                call.span,
+            )?;
+            Ok(left_var)
+        }
+        FuncType::UnwrapRight | FuncType::Unwrap => {
+            debug_assert!(call.args.len() == 1);
+            let arg_ty = infer_expression(ctx, &call.args[0])?;
+            let right_var = ctx.fresh();
+            ctx.unify(
+                &arg_ty,
+                &Term::Sum(Box::new(ctx.fresh()), Box::new(right_var.clone())),
+                call.span,
+            )?;
+            Ok(right_var)
+        }
+    }
+}
+
+/// Unlike jets, `fold`/`map`'s signatures are fully owned by this crate (see
+/// [`crate::builtin::BuiltIn`]), so their arguments really can be checked
+/// against the real call shape instead of left as independent fresh
+/// variables.
+///
+/// `args[0]` is required to already be a literal list, so by the time this
+/// runs it has always been inferred to a concrete [`Term::List`] (its bound
+/// comes straight from the literal's length, never an unresolved variable);
+/// anything else is a genuine type error.
+///
+/// The body (`args[1]` for `map`, `args[2]` for `fold`) has no way to refer
+/// to the element (or, for `fold`, the accumulator) by name at all: there is
+/// no parameter-binding mechanism threaded through `resolve`/`infer`/
+/// `compile` for it, only `crate::builtin::compile_body`'s closed scope. It
+/// is inferred against a brand new, empty binding stack (see
+/// [`infer_closed_body`]), mirroring that closed scope and
+/// `crate::resolve::resolve_builtin_call`'s closed `Resolver`, so a
+/// reference to an outer `let`-bound variable is a real
+/// `Error::UndefinedVariable` here instead of silently type-checking against
+/// the wrong (ambient) scope. Beyond that, its inferred type can't be tied
+/// back to the list's element type here, and in practice only a body that
+/// doesn't reference the element (a constant, or one built only from
+/// witnesses) type-checks through `compile.rs`.
+fn infer_builtin(
+    ctx: &mut Context,
+    name: &Identifier,
+    args: &[Expression],
+    span: Span,
+) -> Result<Term, RichError> {
+    let builtin = crate::builtin::BuiltIn::from_name(name)
+        .ok_or_else(|| Error::FuncNotFound(name.clone()))
+        .with_span(span)?;
+    if args.len() != builtin.arg_count() {
+        return Err(Error::InvalidNumberOfArguments(
+            builtin.arg_count(),
+            args.len(),
+        ))
+        .with_span(span);
+    }
+
+    let list_ty = infer_expression(ctx, &args[0])?.resolve(&ctx.subst);
+    let bound = match list_ty {
+        Term::List(_, bound) => bound,
+        other => return Err(Error::TypeValueMismatch(other.into_type())).with_span(span),
+    };
+    match builtin {
+        crate::builtin::BuiltIn::Map => {
+            infer_closed_body(ctx, &args[1])?;
+            Ok(Term::List(Box::new(ctx.fresh()), bound))
+        }
+        crate::builtin::BuiltIn::Fold => {
+            // A fold's result always has the accumulator's own type: real,
+            // no-guesswork unification, unlike the body's internal shape.
+            let seed_ty = infer_expression(ctx, &args[1])?;
+            infer_closed_body(ctx, &args[2])?;
+            Ok(seed_ty)
+        }
+    }
+}
+
+/// Infers `body` against a brand new, empty binding stack, so it can't see
+/// any of the ambient `let`-bound variables in scope at the call site.
+fn infer_closed_body(ctx: &mut Context, body: &Expression) -> Result<(), RichError> {
+    let outer = std::mem::replace(&mut ctx.bindings, vec![Vec::new()]);
+    let result = infer_expression(ctx, body);
+    ctx.bindings = outer;
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unify_matching_constructors_succeeds() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let a = Term::Product(Box::new(Term::Unit), Box::new(Term::UInt(UIntType::U32)));
+        let b = Term::Product(Box::new(Term::Unit), Box::new(Term::UInt(UIntType::U32)));
+        assert!(ctx.unify(&a, &b, span).is_ok());
+    }
+
+    #[test]
+    fn unify_mismatched_constructors_fails() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        assert!(ctx.unify(&Term::Unit, &Term::UInt(UIntType::U32), span).is_err());
+    }
+
+    #[test]
+    fn false_and_true_infer_to_sum_of_units_like_none() {
+        // Must match `None`'s encoding (and what `compile.rs` actually
+        // builds via `ProgNode::_false()`/`_true()`), not plain `Unit`, or a
+        // bool unified against a sum-shaped expectation is wrongly rejected.
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let expected = Term::Sum(Box::new(Term::Unit), Box::new(Term::Unit));
+        assert_eq!(
+            infer_single(&mut ctx, &SingleExpressionInner::False, span).unwrap(),
+            expected
+        );
+        assert_eq!(
+            infer_single(&mut ctx, &SingleExpressionInner::True, span).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn unify_lists_with_different_bounds_but_same_element_type_succeeds() {
+        // A literal's own bound (from its length) must still be able to
+        // reconcile with a larger declared bound: `compile.rs` pads the
+        // literal out to whatever `reqd_ty` asks for, using `reqd_ty`
+        // directly rather than anything unify records here.
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let small = Term::List(Box::new(Term::UInt(UIntType::U32)), NonZeroPow2Usize::next(4));
+        let large = Term::List(Box::new(Term::UInt(UIntType::U32)), NonZeroPow2Usize::next(8));
+        assert!(ctx.unify(&large, &small, span).is_ok());
+    }
+
+    #[test]
+    fn unify_lists_with_mismatched_element_types_still_fails() {
+        // Relaxing the bound check must not also relax element-type
+        // checking: a `List<Unit>` and a `List<u32>` are still incompatible
+        // no matter their bounds.
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let a = Term::List(Box::new(Term::Unit), NonZeroPow2Usize::next(4));
+        let b = Term::List(
+            Box::new(Term::UInt(UIntType::U32)),
+            NonZeroPow2Usize::next(4),
+        );
+        assert!(ctx.unify(&a, &b, span).is_err());
+    }
+
+    #[test]
+    fn infer_builtin_rejects_unknown_name_instead_of_indexing_args() {
+        // An empty `args` slice used to be indexed into unconditionally;
+        // this must come back as a real error instead of panicking, even
+        // before `name` is known to be a builtin at all.
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let name = Identifier::from("not_a_builtin");
+        assert!(infer_builtin(&mut ctx, &name, &[], span).is_err());
+    }
+
+    #[test]
+    fn infer_builtin_rejects_wrong_arg_count_instead_of_indexing_args() {
+        // `map` needs 2 args; calling it with 0 used to panic on `args[0]`
+        // rather than reporting a mismatched argument count.
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let name = Identifier::from("map");
+        assert!(infer_builtin(&mut ctx, &name, &[], span).is_err());
+    }
+
+    #[test]
+    fn occurs_check_rejects_self_referential_binding() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let var_term = ctx.fresh();
+        let var = match var_term {
+            Term::Var(v) => v,
+            _ => unreachable!("fresh always returns Term::Var"),
+        };
+        let recursive = Term::Product(Box::new(var_term.clone()), Box::new(Term::Unit));
+        assert!(ctx.bind(var, recursive, span).is_err());
+    }
+
+    #[test]
+    fn uint_var_rejects_non_uint_binding() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let int_var = ctx.fresh_uint();
+        let array_ty = Term::Array(Box::new(Term::UInt(UIntType::U32)), 4);
+        assert!(ctx.unify(&int_var, &array_ty, span).is_err());
+    }
+
+    #[test]
+    fn uint_var_accepts_uint_binding() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let int_var = ctx.fresh_uint();
+        assert!(ctx.unify(&int_var, &Term::UInt(UIntType::U64), span).is_ok());
+    }
+
+    #[test]
+    fn uint_constraint_propagates_through_unresolved_var() {
+        let mut ctx = Context::new();
+        let span = Span::default();
+        let int_var = ctx.fresh_uint();
+        let other_var = ctx.fresh();
+        // Unifying the uint-constrained var with a still-unresolved var
+        // should carry the constraint over to that other variable too.
+        ctx.unify(&int_var, &other_var, span).unwrap();
+        let array_ty = Term::Array(Box::new(Term::Unit), 2);
+        assert!(ctx.unify(&other_var, &array_ty, span).is_err());
+    }
+}
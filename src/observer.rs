@@ -0,0 +1,149 @@
+//! Compiler instrumentation hooks.
+//!
+//! [`Observer`] lets callers watch how a Simfony [`Expression`] is turned
+//! into Simplicity combinators without having to sprinkle `eprintln!` calls
+//! through `compile.rs` and recompile. It is threaded through the `eval`
+//! functions as a `&mut dyn Observer`; [`NoopObserver`] is the default so
+//! existing callers of [`crate::compile`] are unaffected.
+
+use crate::parse::{Identifier, SingleExpressionInner, Span};
+use crate::ProgNode;
+
+/// A hook invoked at each construction site during compilation.
+///
+/// All methods have no-op default implementations, so an implementor only
+/// needs to override the ones it cares about.
+pub trait Observer {
+    /// Called before a [`SingleExpressionInner`] is compiled.
+    fn on_expression_start(&mut self, _expr: &SingleExpressionInner, _span: Span) {}
+
+    /// Called once the Simplicity node for a construct has been built.
+    fn on_node_built(&mut self, _node: &ProgNode, _span: Span) {}
+
+    /// Called when a variable reference is resolved to a `take`/`drop` chain
+    /// in the environment.
+    fn on_scope_lookup(&mut self, _identifier: &Identifier, _node: &ProgNode) {}
+}
+
+/// The default [`Observer`] that does nothing; used wherever a caller does
+/// not care to instrument compilation.
+#[derive(Debug, Default)]
+pub struct NoopObserver;
+
+impl Observer for NoopObserver {}
+
+/// An [`Observer`] that prints an indented disassembly of every construct as
+/// it is compiled: the source span and Simfony construct, followed by the
+/// resulting combinator and its inferred arrow type.
+///
+/// This makes it possible to see exactly which `take`/`drop` chain a given
+/// variable reference resolved to, and to watch where
+/// unification actually happens for the pair-construction sites that used to
+/// be marked "should always unify".
+#[derive(Debug, Default)]
+pub struct DisassemblingObserver {
+    depth: usize,
+}
+
+impl DisassemblingObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn indent(&self) -> String {
+        "  ".repeat(self.depth)
+    }
+}
+
+impl Observer for DisassemblingObserver {
+    fn on_expression_start(&mut self, expr: &SingleExpressionInner, span: Span) {
+        println!("{}{:?} @ {}", self.indent(), expr, span);
+        self.depth += 1;
+    }
+
+    fn on_node_built(&mut self, node: &ProgNode, span: Span) {
+        self.depth = self.depth.saturating_sub(1);
+        println!(
+            "{}=> {} : {}",
+            self.indent(),
+            combinator_name(node),
+            node.arrow()
+        );
+        let _ = span;
+    }
+
+    fn on_scope_lookup(&mut self, identifier: &Identifier, node: &ProgNode) {
+        println!(
+            "{}{} resolved to {}",
+            self.indent(),
+            identifier,
+            combinator_name(node)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgNode;
+
+    /// One `on_expression_start` must be matched by exactly one
+    /// `on_node_built`, or `depth` drifts and desyncs every subsequent
+    /// indentation level for the rest of the disassembly. A `FuncCall`
+    /// used to report twice (once from `FuncCall::eval`, once from its
+    /// caller), which this exercises directly against the observer without
+    /// needing a real compile.
+    #[test]
+    fn paired_start_and_built_calls_leave_depth_at_zero() {
+        let mut observer = DisassemblingObserver::new();
+        let span = Span::default();
+        let node = ProgNode::unit();
+
+        observer.on_expression_start(&SingleExpressionInner::Unit, span);
+        observer.on_node_built(&node, span);
+        assert_eq!(observer.depth, 0);
+
+        observer.on_expression_start(&SingleExpressionInner::Unit, span);
+        observer.on_expression_start(&SingleExpressionInner::Unit, span);
+        observer.on_node_built(&node, span);
+        observer.on_node_built(&node, span);
+        assert_eq!(observer.depth, 0);
+    }
+
+    #[test]
+    fn unmatched_node_built_saturates_instead_of_wrapping() {
+        let mut observer = DisassemblingObserver::new();
+        let span = Span::default();
+        let node = ProgNode::unit();
+
+        // A stray extra `on_node_built` (the bug this request fixed) must
+        // not wrap `depth` around to `usize::MAX`.
+        observer.on_node_built(&node, span);
+        assert_eq!(observer.depth, 0);
+    }
+}
+
+/// Names the outermost combinator of a compiled node, for disassembly
+/// output (`comp`, `pair`, `case`, `injl`, `take`, `drop`, `witness`, `jet`,
+/// `const_word`, ...).
+fn combinator_name(node: &ProgNode) -> &'static str {
+    use simplicity::node::Inner;
+    match node.inner() {
+        Inner::Iden => "iden",
+        Inner::Unit => "unit",
+        Inner::InjL(..) => "injl",
+        Inner::InjR(..) => "injr",
+        Inner::Take(..) => "take",
+        Inner::Drop(..) => "drop",
+        Inner::Comp(..) => "comp",
+        Inner::Pair(..) => "pair",
+        Inner::Case(..) => "case",
+        Inner::AssertL(..) => "assertl",
+        Inner::AssertR(..) => "assertr",
+        Inner::Disconnect(..) => "disconnect",
+        Inner::Fail(..) => "fail",
+        Inner::Witness(..) => "witness",
+        Inner::Jet(..) => "jet",
+        Inner::Word(..) => "const_word",
+    }
+}
@@ -0,0 +1,349 @@
+//! One-pass variable resolution.
+//!
+//! `SingleExpressionInner::Match` used to clone the entire scope stack twice
+//! per match (once per arm), and `GlobalScope::get` re-walked the whole
+//! stack and rebuilt a fresh `take`/`drop` chain on every variable use; both
+//! are quadratic on deeply nested blocks. This pass instead walks the
+//! program once, assigns each binding a slot on a single shared stack, and
+//! records for every `Variable` occurrence the de Bruijn-style path
+//! (a sequence of `take`/`drop` turns) that locates it. `eval` then just
+//! consumes the stored path instead of searching.
+
+use std::collections::HashMap;
+
+use crate::array::Direction;
+use crate::error::{Error, RichError, WithSpan};
+use crate::parse::{
+    Expression, ExpressionInner, FuncCall, FuncType, Identifier, Pattern, Program,
+    SingleExpressionInner, Span, Statement,
+};
+use crate::scope::turns_to_program;
+use crate::ProgNode;
+
+/// The environment path for one `Variable` occurrence.
+#[derive(Debug, Clone)]
+pub struct VarPath(Vec<Direction>);
+
+impl VarPath {
+    /// Builds the `take`/`drop`/`iden` chain this path describes.
+    pub fn to_program(&self) -> ProgNode {
+        turns_to_program(&mut self.0.clone())
+    }
+}
+
+/// Resolved paths for every `Variable` occurrence in a program, keyed by its
+/// span.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    paths: HashMap<Span, VarPath>,
+}
+
+impl Resolution {
+    pub fn get(&self, span: Span) -> Option<&VarPath> {
+        self.paths.get(&span)
+    }
+}
+
+/// A single shared stack of binding slots, one per `let` statement or bound
+/// match-arm pattern. Blocks push and pop around their own bindings; match
+/// arms push their own slot on top of the shared stack and pop it again
+/// once resolved, instead of cloning everything beneath it.
+#[derive(Default)]
+struct Resolver {
+    bindings: Vec<Pattern>,
+    paths: HashMap<Span, VarPath>,
+}
+
+impl Resolver {
+    fn mark(&self) -> usize {
+        self.bindings.len()
+    }
+
+    fn truncate(&mut self, mark: usize) {
+        self.bindings.truncate(mark);
+    }
+
+    fn push(&mut self, pattern: Pattern) {
+        self.bindings.push(pattern);
+    }
+
+    /// Finds `identifier`'s slot (most recently pushed wins) and builds the
+    /// full path: one `Right` turn for every slot pushed after it (to drop
+    /// past newer bindings), a `Left` turn into the slot itself, then the
+    /// local path within that slot's own pattern.
+    fn resolve(&self, identifier: &Identifier) -> Option<VarPath> {
+        for (depth, pattern) in self.bindings.iter().rev().enumerate() {
+            if let Some(local_path) = pattern.get_path(identifier) {
+                let mut path = vec![Direction::Right; depth];
+                path.push(Direction::Left);
+                path.extend(local_path);
+                return Some(VarPath(path));
+            }
+        }
+        None
+    }
+
+    fn record(&mut self, span: Span, identifier: &Identifier) -> Result<(), RichError> {
+        let path = self
+            .resolve(identifier)
+            .ok_or_else(|| Error::UndefinedVariable(identifier.clone()))
+            .with_span(span)?;
+        self.paths.insert(span, path);
+        Ok(())
+    }
+}
+
+/// Walks `program` once, resolving every `Variable` occurrence.
+pub fn resolve_program(program: &Program) -> Result<Resolution, RichError> {
+    resolve_program_with_tail(&program.statements, None)
+}
+
+/// Like [`resolve_program`], but additionally resolves a trailing expression
+/// that is not itself one of `stmts` (e.g. the REPL's next input, resolved
+/// against everything entered so far without being stored as a statement).
+pub fn resolve_program_with_tail(
+    stmts: &[Statement],
+    tail: Option<&Expression>,
+) -> Result<Resolution, RichError> {
+    let mut resolver = Resolver::default();
+    resolve_block(&mut resolver, stmts, tail)?;
+    Ok(Resolution {
+        paths: resolver.paths,
+    })
+}
+
+fn resolve_block(
+    resolver: &mut Resolver,
+    stmts: &[Statement],
+    tail: Option<&Expression>,
+) -> Result<(), RichError> {
+    for stmt in stmts {
+        match stmt {
+            Statement::Assignment(assignment) => {
+                resolve_expression(resolver, &assignment.expression)?;
+                resolver.push(assignment.pattern.clone());
+            }
+            Statement::FuncCall(func_call) => {
+                resolve_func_call(resolver, func_call)?;
+            }
+        }
+    }
+    match tail {
+        Some(expr) => resolve_expression(resolver, expr),
+        None => Ok(()),
+    }
+}
+
+fn resolve_expression(resolver: &mut Resolver, expr: &Expression) -> Result<(), RichError> {
+    match &expr.inner {
+        ExpressionInner::BlockExpression(stmts, tail) => {
+            let mark = resolver.mark();
+            let result = resolve_block(resolver, stmts, Some(tail.as_ref()));
+            resolver.truncate(mark);
+            result
+        }
+        ExpressionInner::SingleExpression(single) => {
+            resolve_single(resolver, &single.inner, expr.span)
+        }
+    }
+}
+
+fn resolve_single(
+    resolver: &mut Resolver,
+    inner: &SingleExpressionInner,
+    span: Span,
+) -> Result<(), RichError> {
+    match inner {
+        SingleExpressionInner::Unit
+        | SingleExpressionInner::None
+        | SingleExpressionInner::False
+        | SingleExpressionInner::True
+        | SingleExpressionInner::UnsignedInteger(..)
+        | SingleExpressionInner::BitString(..)
+        | SingleExpressionInner::ByteString(..)
+        | SingleExpressionInner::Witness(..) => Ok(()),
+        SingleExpressionInner::Left(e)
+        | SingleExpressionInner::Right(e)
+        | SingleExpressionInner::Some(e) => resolve_expression(resolver, e),
+        SingleExpressionInner::Product(l, r) => {
+            resolve_expression(resolver, l)?;
+            resolve_expression(resolver, r)
+        }
+        SingleExpressionInner::Variable(identifier) => resolver.record(span, identifier),
+        SingleExpressionInner::FuncCall(call) => resolve_func_call(resolver, call),
+        SingleExpressionInner::Expression(expression) => resolve_expression(resolver, expression),
+        SingleExpressionInner::Match {
+            scrutinee,
+            left,
+            right,
+        } => {
+            resolve_expression(resolver, scrutinee)?;
+            let mark = resolver.mark();
+
+            resolver.push(
+                left.pattern
+                    .get_identifier()
+                    .cloned()
+                    .map(Pattern::Identifier)
+                    .unwrap_or(Pattern::Ignore),
+            );
+            let left_result = resolve_expression(resolver, &left.expression);
+            resolver.truncate(mark);
+            left_result?;
+
+            resolver.push(
+                right
+                    .pattern
+                    .get_identifier()
+                    .cloned()
+                    .map(Pattern::Identifier)
+                    .unwrap_or(Pattern::Ignore),
+            );
+            let right_result = resolve_expression(resolver, &right.expression);
+            resolver.truncate(mark);
+            right_result
+        }
+        SingleExpressionInner::Array(elements) | SingleExpressionInner::List(elements) => {
+            for el in elements {
+                resolve_expression(resolver, el)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn resolve_func_call(resolver: &mut Resolver, call: &FuncCall) -> Result<(), RichError> {
+    match &call.func_type {
+        FuncType::BuiltIn(name) => resolve_builtin_call(resolver, name, &call.args),
+        _ => {
+            for arg in &call.args {
+                resolve_expression(resolver, arg)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `fold`/`map`'s body argument (`args[1]` for `map`, `args[2]` for `fold`)
+/// is compiled against its own closed scope (see
+/// `crate::builtin::compile_body`): there is no parameter binding for the
+/// element (or, for `fold`, the accumulator) at all, and the body must not
+/// see the call site's `let`-bound variables either, since at compile time
+/// it is evaluated against the element's (or element-and-accumulator's)
+/// type, not the ambient environment. The list and seed arguments still
+/// resolve against the ambient `resolver` as normal; the body resolves
+/// against a brand new, empty one, so a reference to an outer variable
+/// becomes a real `Error::UndefinedVariable` right here, instead of a stale
+/// `VarPath` built for the wrong environment shape that would otherwise only
+/// fail later, confusingly, inside `ProgNode::comp`'s type check.
+fn resolve_builtin_call(
+    resolver: &mut Resolver,
+    name: &Identifier,
+    args: &[Expression],
+) -> Result<(), RichError> {
+    let body_index = crate::builtin::BuiltIn::from_name(name)
+        .filter(|builtin| args.len() == builtin.arg_count())
+        .map(|_| args.len() - 1);
+
+    for (index, arg) in args.iter().enumerate() {
+        if Some(index) == body_index {
+            continue;
+        }
+        resolve_expression(resolver, arg)?;
+    }
+    if let Some(body_index) = body_index {
+        let mut closed = Resolver::default();
+        resolve_expression(&mut closed, &args[body_index])?;
+        resolver.paths.extend(closed.paths);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(name: &str) -> Identifier {
+        Identifier::from(name)
+    }
+
+    /// Mirrors what `resolve_single`'s `Match` arm pushes per arm: a single
+    /// `Pattern::Identifier` slot for a bound arm, or `Pattern::Ignore` for
+    /// an arm whose pattern doesn't bind a name.
+    fn push_arm(resolver: &mut Resolver, bound: Option<&str>) {
+        let pattern = bound
+            .map(ident)
+            .map(Pattern::Identifier)
+            .unwrap_or(Pattern::Ignore);
+        resolver.push(pattern);
+    }
+
+    /// A two-level nested match, the shape the `Match` arm in
+    /// `resolve_single` walks one scope at a time:
+    ///
+    /// ```text
+    /// let a = ...;
+    /// match x {
+    ///     Left(b) => match y {
+    ///         Left(c) => a,  // reached through two arms
+    ///         Right(d) => a, // reached through two sibling arms
+    ///     },
+    ///     Right(e) => a,     // reached through one arm only
+    /// }
+    /// ```
+    ///
+    /// `a` is pushed once, before any arm, and each arm's bindings are
+    /// popped again (`truncate`) once that arm is resolved - mirroring
+    /// `resolve_single`'s `Match` handling exactly. Two references to `a`
+    /// from the same stack depth (the two inner arms) must resolve to the
+    /// same path, and popping an arm's bindings must fully undo their
+    /// effect on later lookups (the shallower outer-right reference must
+    /// resolve as if the inner match had never existed).
+    #[test]
+    fn nested_match_resolves_outer_binding_consistently_across_sibling_arms() {
+        let mut resolver = Resolver::default();
+        resolver.push(Pattern::Identifier(ident("a")));
+        let top = resolver.mark();
+
+        // Outer left arm: binds `b`, then descends into the inner match.
+        push_arm(&mut resolver, Some("b"));
+        let middle = resolver.mark();
+
+        push_arm(&mut resolver, Some("c"));
+        let a_under_left_left = resolver.resolve(&ident("a")).unwrap();
+        let b_under_left_left = resolver.resolve(&ident("b")).unwrap();
+        resolver.truncate(middle);
+
+        push_arm(&mut resolver, Some("d"));
+        let a_under_left_right = resolver.resolve(&ident("a")).unwrap();
+        resolver.truncate(middle);
+
+        resolver.truncate(top);
+
+        // Outer right arm: no inner match, so this is one arm shallower
+        // than the two lookups above.
+        push_arm(&mut resolver, Some("e"));
+        let a_under_right = resolver.resolve(&ident("a")).unwrap();
+        resolver.truncate(top);
+
+        // Same identifier, same stack depth (one `let`, two arms stacked
+        // above it) from two sibling inner arms: same path.
+        assert_eq!(a_under_left_left.0.len(), a_under_left_right.0.len());
+        assert!(matches!(a_under_left_left.0.last(), Some(Direction::Left)));
+        assert!(matches!(a_under_left_right.0.last(), Some(Direction::Left)));
+
+        // `b` sits one arm closer to `a` than the inner arms do, so its
+        // path is shorter than the simultaneous `a` lookup from there.
+        assert!(b_under_left_left.0.len() < a_under_left_left.0.len());
+
+        // Popping the inner match's bindings (`truncate(top)`) must fully
+        // restore the outer scope: the right arm's `a` is one arm shallower
+        // than the left arm's, not contaminated by the popped `b`/`c`/`d`.
+        assert_eq!(a_under_right.0.len() + 1, a_under_left_left.0.len());
+
+        // Bindings popped by `truncate` are gone, not just shadowed.
+        assert!(resolver.resolve(&ident("b")).is_none());
+        assert!(resolver.resolve(&ident("c")).is_none());
+        assert!(resolver.resolve(&ident("d")).is_none());
+    }
+}
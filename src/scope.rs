@@ -2,15 +2,18 @@ use crate::array::{DirectedTree, Direction};
 use crate::parse::{Identifier, Pattern, WitnessName};
 use crate::{named::ProgExt, ProgNode};
 
-/// Tracker of variable bindings and witness names.
+/// Tracker of witness names.
 ///
-/// Internally there is a stack of scopes.
-/// A new scope is pushed for each (nested) block expression.
+/// Variable bindings are no longer tracked here: [`crate::resolve`] walks
+/// the program once up front and assigns each `Variable` occurrence a
+/// precomputed environment path (see [`crate::resolve::VarPath`]), so
+/// `eval` never needs to search a scope stack or clone it for a match arm.
 ///
-/// Bindings from higher scopes (in the stack) overwrite bindings from lower scopes.
+/// Internally there is a stack of scopes, one pushed per (nested) block
+/// expression, purely to keep witness names scoped the same way variables
+/// used to be.
 #[derive(Debug, Clone)]
 pub struct GlobalScope {
-    variables: Vec<Vec<Pattern>>,
     witnesses: Vec<Vec<WitnessName>>,
 }
 
@@ -24,14 +27,12 @@ impl GlobalScope {
     /// Creates a new [`GlobalScope`].
     pub fn new() -> Self {
         GlobalScope {
-            variables: vec![Vec::new()],
             witnesses: vec![Vec::new()],
         }
     }
 
     /// Pushes a new scope to the stack.
     pub fn push_scope(&mut self) {
-        self.variables.push(Vec::new());
         self.witnesses.push(Vec::new());
     }
 
@@ -41,90 +42,13 @@ impl GlobalScope {
     ///
     /// Panics if the stack is empty.
     pub fn pop_scope(&mut self) {
-        self.variables.pop().expect("Popping scope zero");
         self.witnesses.pop().expect("Popping scope zero");
     }
 
-    /// Pushes a new variable to the latest scope.
-    pub fn insert(&mut self, pattern: Pattern) {
-        self.variables.last_mut().unwrap().push(pattern);
-    }
-
     /// Pushes a new witness to the latest scope.
     pub fn insert_witness(&mut self, key: WitnessName) {
         self.witnesses.last_mut().unwrap().push(key);
     }
-
-    /// Get a Simplicity expression that returns the value of the given `identifier`.
-    ///
-    /// The expression is a sequence of `take` and `drop` followed by `iden`,
-    /// which extracts the seeked value from the environment.
-    ///
-    /// The environment starts as the unit value.
-    ///
-    /// Each let statement updates the environment to become
-    /// the product of the assigned value (left) and of the previous environment (right).
-    ///
-    /// (Block) expressions consume the environment and return their output.
-    ///
-    /// ## Example
-    ///
-    /// ```
-    /// let a: u8 = 0;
-    /// let b = {
-    ///     let b: u8 = 1;
-    ///     let c: u8 = 2;
-    ///     a  // here we seek the value of `a`
-    /// };
-    /// ```
-    ///
-    /// The stack of scopes looks like this:
-    ///
-    /// `[a] [b c]`
-    ///
-    /// The environment looks like this:
-    ///
-    /// ```text
-    ///   .
-    ///  / \
-    /// C   .
-    ///    / \
-    ///   B   .
-    ///      / \
-    ///     A   1
-    /// ```
-    ///
-    /// To extract `a`, we need the expression `drop drop take iden`.
-    ///
-    /// ## Panics
-    ///
-    /// The `identifier` is undefined.
-    pub fn get(&self, identifier: &Identifier) -> ProgNode {
-        let mut pos = 0;
-
-        // Highest scope has precedence
-        for scope in self.variables.iter().rev() {
-            // Last let statement has precedence
-            if let Some((pattern_pos, mut expr)) =
-                scope.iter().rev().enumerate().find_map(|(idx, pattern)| {
-                    pattern.get_program(identifier).map(|expr| (idx, expr))
-                })
-            {
-                pos += pattern_pos;
-
-                expr = ProgNode::take(expr);
-                for _ in 0..pos {
-                    expr = ProgNode::drop_(expr);
-                }
-
-                return expr;
-            } else {
-                pos += scope.len();
-            }
-        }
-
-        panic!("\"{identifier}\" is undefined");
-    }
 }
 
 impl Pattern {
@@ -135,7 +59,13 @@ impl Pattern {
         }
     }
 
-    pub fn get_program(&self, identifier: &Identifier) -> Option<ProgNode> {
+    /// Finds `identifier` inside this pattern and returns the root-to-leaf
+    /// sequence of `take`/`drop` turns that locates it, without building a
+    /// [`ProgNode`].
+    ///
+    /// This is the piece [`crate::resolve`] reuses to extend a single
+    /// pattern's local path into a path across the whole binding stack.
+    pub fn get_path(&self, identifier: &Identifier) -> Option<Vec<Direction>> {
         let base_pattern = self.to_base();
         let directed_tree = DirectedTree::from(&base_pattern);
         let equals_identifier = |pattern: &Pattern| {
@@ -144,18 +74,30 @@ impl Pattern {
                 .map(|i| i == identifier)
                 .unwrap_or(false)
         };
-        let (_, mut path) = directed_tree.find(equals_identifier)?;
+        directed_tree.find(equals_identifier).map(|(_, path)| path)
+    }
 
-        let mut output = ProgNode::iden();
-        while let Some(direction) = path.pop() {
-            match direction {
-                Direction::Left => output = ProgNode::take(output),
-                Direction::Right => output = ProgNode::drop_(output),
-                Direction::Down => unreachable!("There are no unary patterns"),
-                Direction::Index(..) => unreachable!("Base patterns exclude arrays"),
-            }
-        }
+    /// Get a Simplicity expression that returns the value of `identifier`
+    /// within this pattern alone, ignoring any outer bindings.
+    pub fn get_program(&self, identifier: &Identifier) -> Option<ProgNode> {
+        let mut path = self.get_path(identifier)?;
+        Some(turns_to_program(&mut path))
+    }
+}
 
-        Some(output)
+/// Builds the `take`/`drop`/`iden` chain for a root-to-leaf sequence of
+/// turns, consuming it back-to-front the same way [`Pattern::get_program`]
+/// always has: the leaf turn ends up closest to `iden`, and the root turn
+/// becomes the outermost combinator.
+pub fn turns_to_program(path: &mut Vec<Direction>) -> ProgNode {
+    let mut output = ProgNode::iden();
+    while let Some(direction) = path.pop() {
+        match direction {
+            Direction::Left => output = ProgNode::take(output),
+            Direction::Right => output = ProgNode::drop_(output),
+            Direction::Down => unreachable!("There are no unary patterns"),
+            Direction::Index(..) => unreachable!("Base patterns exclude arrays"),
+        }
     }
+    output
 }